@@ -0,0 +1,184 @@
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+// Attachments are encrypted in fixed-size frames, each sealed on its own
+// with a nonce taken from the frame index. `encrypt_attachment` and
+// `decrypt_attachment` take and return the whole payload in memory; they
+// do not stream.
+const FRAME_SIZE: usize = 64 * 1024;
+const ATTACHMENT_KEY_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum AttachmentError {
+    InvalidKeyLength(usize),
+    KeyFingerprintMismatch,
+    CorruptFrame(base64::DecodeError),
+    AesError(aes_gcm::Error),
+}
+
+impl fmt::Display for AttachmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttachmentError::InvalidKeyLength(len) => {
+                write!(f, "attachment key must be 256 bits (32 bytes), got {} bytes", len)
+            }
+            AttachmentError::KeyFingerprintMismatch => {
+                write!(f, "presented key does not match the attachment's stored fingerprint")
+            }
+            AttachmentError::CorruptFrame(e) => write!(f, "attachment frame is not valid base64: {}", e),
+            AttachmentError::AesError(e) => write!(f, "AES error: {}", e),
+        }
+    }
+}
+
+impl Error for AttachmentError {}
+
+/// A media payload encrypted under a caller-supplied key that the server
+/// never stores: only the ciphertext frames and a fingerprint of the key
+/// (used to reject the wrong key on decrypt) are persisted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedAttachment {
+    pub frames: Vec<String>,
+    pub key_fingerprint: String,
+}
+
+// Derive the actual content-encryption key from the caller-supplied key and
+// the message id, so the same caller key never directly touches the AEAD.
+fn derive_content_key(key: &[u8; ATTACHMENT_KEY_LEN], message_id: &str) -> [u8; 32] {
+    let hkdf = Hkdf::<Sha256>::new(Some(message_id.as_bytes()), key);
+    let mut cek = [0u8; 32];
+    hkdf.expand(b"ulta-secure-messenger attachment", &mut cek)
+        .expect("HKDF expand with fixed-size output cannot fail");
+    cek
+}
+
+fn key_fingerprint(key: &[u8; ATTACHMENT_KEY_LEN]) -> String {
+    hex::encode(Sha256::digest(key))
+}
+
+fn nonce_for_frame(index: u64) -> [u8; 12] {
+    let mut bytes = [0u8; 12];
+    bytes[4..].copy_from_slice(&index.to_be_bytes());
+    bytes
+}
+
+fn validate_key(key: &[u8]) -> Result<[u8; ATTACHMENT_KEY_LEN], AttachmentError> {
+    key.try_into()
+        .map_err(|_| AttachmentError::InvalidKeyLength(key.len()))
+}
+
+/// Encrypt `data` under `key` (a 256-bit caller-supplied key, never stored
+/// by the server) as one frame per `FRAME_SIZE` chunk. The whole of `data`
+/// and all resulting frames are held in memory at once.
+pub fn encrypt_attachment(
+    key: &[u8],
+    message_id: &str,
+    data: &[u8],
+) -> Result<EncryptedAttachment, AttachmentError> {
+    let key = validate_key(key)?;
+    let content_key = derive_content_key(&key, message_id);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&content_key));
+
+    let frames = data
+        .chunks(FRAME_SIZE)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let nonce_bytes = nonce_for_frame(index as u64);
+            let nonce = Nonce::from_slice(&nonce_bytes);
+            cipher
+                .encrypt(nonce, chunk)
+                .map(base64::encode)
+                .map_err(AttachmentError::AesError)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(EncryptedAttachment {
+        frames,
+        key_fingerprint: key_fingerprint(&key),
+    })
+}
+
+/// Decrypt an `EncryptedAttachment`, rejecting the request if `key`'s
+/// fingerprint does not match the one the attachment was stored with.
+pub fn decrypt_attachment(
+    key: &[u8],
+    message_id: &str,
+    attachment: &EncryptedAttachment,
+) -> Result<Vec<u8>, AttachmentError> {
+    let key = validate_key(key)?;
+    if key_fingerprint(&key) != attachment.key_fingerprint {
+        return Err(AttachmentError::KeyFingerprintMismatch);
+    }
+
+    let content_key = derive_content_key(&key, message_id);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&content_key));
+
+    let mut plaintext = Vec::new();
+    for (index, frame) in attachment.frames.iter().enumerate() {
+        let nonce_bytes = nonce_for_frame(index as u64);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+        let ciphertext = base64::decode(frame).map_err(AttachmentError::CorruptFrame)?;
+        let chunk = cipher
+            .decrypt(nonce, ciphertext.as_ref())
+            .map_err(AttachmentError::AesError)?;
+        plaintext.extend_from_slice(&chunk);
+    }
+
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_small_payload() {
+        let key = [0x11u8; 32];
+        let data = b"a short attachment";
+
+        let encrypted = encrypt_attachment(&key, "msg-1", data).unwrap();
+        let decrypted = decrypt_attachment(&key, "msg-1", &encrypted).unwrap();
+
+        assert_eq!(decrypted, data);
+    }
+
+    #[test]
+    fn test_roundtrip_multi_frame_payload() {
+        let key = [0x22u8; 32];
+        let data = vec![0x99u8; FRAME_SIZE * 3 + 17];
+
+        let encrypted = encrypt_attachment(&key, "msg-2", &data).unwrap();
+        assert_eq!(encrypted.frames.len(), 4);
+
+        let decrypted = decrypt_attachment(&key, "msg-2", &encrypted).unwrap();
+        assert_eq!(decrypted, data);
+    }
+
+    #[test]
+    fn test_wrong_key_is_rejected() {
+        let key = [0x33u8; 32];
+        let wrong_key = [0x44u8; 32];
+        let data = b"secret media bytes";
+
+        let encrypted = encrypt_attachment(&key, "msg-3", data).unwrap();
+        let result = decrypt_attachment(&wrong_key, "msg-3", &encrypted);
+
+        assert!(matches!(result, Err(AttachmentError::KeyFingerprintMismatch)));
+    }
+
+    #[test]
+    fn test_invalid_key_length_is_rejected() {
+        let short_key = [0u8; 16];
+        let result = encrypt_attachment(&short_key, "msg-4", b"data");
+
+        assert!(matches!(result, Err(AttachmentError::InvalidKeyLength(16))));
+    }
+}
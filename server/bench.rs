@@ -0,0 +1,255 @@
+
+use crate::encryption_engine::RustEncryptionEngine;
+use crate::message_processor::{Message, MessageType, RustMessageProcessor};
+use std::time::Instant;
+
+/// Names recognized on the `benchmark` CLI command; pass one or more to run
+/// a subset, or none to run all of them.
+pub const ALL_BENCHMARKS: [&str; 6] = [
+    "rsa-keygen",
+    "hybrid-encrypt",
+    "hybrid-decrypt",
+    "bulk-aes-gcm",
+    "blake3-hash",
+    "queue-throughput",
+];
+
+/// Aggregated per-iteration timings for a single named benchmark, separating
+/// setup (done once, outside the timed region) from the repeated measurement.
+pub struct BenchReport {
+    pub name: String,
+    pub samples_ns: Vec<u64>,
+    pub bytes_per_iteration: Option<u64>,
+}
+
+impl BenchReport {
+    fn new(name: &str, samples_ns: Vec<u64>, bytes_per_iteration: Option<u64>) -> Self {
+        Self {
+            name: name.to_string(),
+            samples_ns,
+            bytes_per_iteration,
+        }
+    }
+
+    pub fn min_ns(&self) -> u64 {
+        self.samples_ns.iter().copied().min().unwrap_or(0)
+    }
+
+    pub fn median_ns(&self) -> u64 {
+        let mut sorted = self.samples_ns.clone();
+        sorted.sort_unstable();
+        if sorted.is_empty() {
+            return 0;
+        }
+        sorted[sorted.len() / 2]
+    }
+
+    pub fn mean_ns(&self) -> f64 {
+        if self.samples_ns.is_empty() {
+            return 0.0;
+        }
+        self.samples_ns.iter().sum::<u64>() as f64 / self.samples_ns.len() as f64
+    }
+
+    pub fn ops_per_sec(&self) -> f64 {
+        let mean_secs = self.mean_ns() / 1_000_000_000.0;
+        if mean_secs == 0.0 {
+            0.0
+        } else {
+            1.0 / mean_secs
+        }
+    }
+
+    pub fn mb_per_sec(&self) -> Option<f64> {
+        self.bytes_per_iteration.map(|bytes| {
+            let mb = bytes as f64 / (1024.0 * 1024.0);
+            mb * self.ops_per_sec()
+        })
+    }
+
+    pub fn print(&self) {
+        println!("  {}:", self.name);
+        println!(
+            "    min={:?} median={:?} mean={:?}",
+            std::time::Duration::from_nanos(self.min_ns()),
+            std::time::Duration::from_nanos(self.median_ns()),
+            std::time::Duration::from_nanos(self.mean_ns() as u64),
+        );
+        println!("    throughput: {:.2} ops/sec", self.ops_per_sec());
+        if let Some(mb_per_sec) = self.mb_per_sec() {
+            println!("    throughput: {:.2} MB/sec", mb_per_sec);
+        }
+    }
+}
+
+// Time `iterations` runs of `work`, recording a nanosecond sample per run.
+// Any per-run setup must happen inside `work` only if it is part of what's
+// being measured; callers that need one-time setup do it before calling this.
+fn time_iterations<F: FnMut()>(iterations: usize, mut work: F) -> Vec<u64> {
+    let mut samples = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        work();
+        samples.push(start.elapsed().as_nanos() as u64);
+    }
+    samples
+}
+
+fn bench_rsa_keygen() -> BenchReport {
+    let engine = RustEncryptionEngine::new();
+    let samples = time_iterations(5, || {
+        engine.generate_rsa_keypair().expect("keygen failed");
+    });
+    BenchReport::new("rsa-keygen", samples, None)
+}
+
+fn bench_hybrid_encrypt() -> BenchReport {
+    let engine = RustEncryptionEngine::new();
+    let (_private_key, public_key) = engine.generate_rsa_keypair().expect("keygen failed");
+    let message = "Performance test message for Rust encryption engine";
+
+    let samples = time_iterations(20, || {
+        engine
+            .encrypt_message(message, &public_key)
+            .expect("encrypt failed");
+    });
+    BenchReport::new("hybrid-encrypt", samples, Some(message.len() as u64))
+}
+
+fn bench_hybrid_decrypt() -> BenchReport {
+    let engine = RustEncryptionEngine::new();
+    let (private_key, public_key) = engine.generate_rsa_keypair().expect("keygen failed");
+    let message = "Performance test message for Rust encryption engine";
+    let encrypted = engine
+        .encrypt_message(message, &public_key)
+        .expect("encrypt failed");
+
+    let samples = time_iterations(20, || {
+        engine
+            .decrypt_message(&encrypted, &private_key)
+            .expect("decrypt failed");
+    });
+    BenchReport::new("hybrid-decrypt", samples, Some(message.len() as u64))
+}
+
+fn bench_bulk_aes_gcm() -> BenchReport {
+    use aes_gcm::{
+        aead::{Aead, AeadCore, KeyInit, OsRng},
+        Aes256Gcm,
+    };
+
+    const PAYLOAD_SIZE: usize = 8 * 1024 * 1024;
+    let key = Aes256Gcm::generate_key(OsRng);
+    let cipher = Aes256Gcm::new(&key);
+    let payload = vec![0x5au8; PAYLOAD_SIZE];
+
+    let samples = time_iterations(10, || {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        cipher.encrypt(&nonce, payload.as_ref()).expect("encrypt failed");
+    });
+    BenchReport::new("bulk-aes-gcm", samples, Some(PAYLOAD_SIZE as u64))
+}
+
+fn bench_blake3_hash() -> BenchReport {
+    const PAYLOAD_SIZE: usize = 16 * 1024 * 1024;
+    let payload = vec![0xa5u8; PAYLOAD_SIZE];
+
+    let samples = time_iterations(10, || {
+        blake3::hash(&payload);
+    });
+    BenchReport::new("blake3-hash", samples, Some(PAYLOAD_SIZE as u64))
+}
+
+// Async equivalent of `time_iterations`: each iteration gets its own fresh
+// processor and chat, so enqueue+drain runs are independently timed rather
+// than folded into one pre-averaged reading.
+async fn time_async_iterations<F, Fut>(iterations: usize, mut work: F) -> Vec<u64>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let mut samples = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        work().await;
+        samples.push(start.elapsed().as_nanos() as u64);
+    }
+    samples
+}
+
+async fn bench_queue_throughput() -> BenchReport {
+    const ITERATIONS: usize = 10;
+    const MESSAGE_COUNT: usize = 500;
+
+    let samples = time_async_iterations(ITERATIONS, || async {
+        let processor = RustMessageProcessor::new(10_000, 50);
+        for i in 0..MESSAGE_COUNT {
+            let message = Message {
+                id: format!("bench_{}", i),
+                chat_id: "bench_chat".to_string(),
+                sender_id: "bench_user".to_string(),
+                content: format!("Benchmark message {}", i),
+                timestamp: 0,
+                message_type: MessageType::Text,
+                attachment: None,
+            };
+            processor.queue_message(message).await.expect("queue failed");
+        }
+        loop {
+            let batch = processor
+                .process_batch("bench_chat")
+                .await
+                .expect("process_batch failed");
+            if batch.is_empty() {
+                break;
+            }
+        }
+    })
+    .await
+    .into_iter()
+    .map(|total_ns| total_ns / MESSAGE_COUNT as u64)
+    .collect();
+
+    BenchReport::new("queue-throughput", samples, None)
+}
+
+/// Run the selected named benchmarks (or all of them if `selected` is empty)
+/// and print a min/median/mean/throughput summary for each.
+pub async fn run(selected: &[String]) {
+    let names: Vec<&str> = if selected.is_empty() {
+        ALL_BENCHMARKS.to_vec()
+    } else {
+        selected.iter().map(|s| s.as_str()).collect()
+    };
+
+    println!("🦀 Rust Statistical Benchmark Harness:");
+    for name in names {
+        let report = match name {
+            "rsa-keygen" => bench_rsa_keygen(),
+            "hybrid-encrypt" => bench_hybrid_encrypt(),
+            "hybrid-decrypt" => bench_hybrid_decrypt(),
+            "bulk-aes-gcm" => bench_bulk_aes_gcm(),
+            "blake3-hash" => bench_blake3_hash(),
+            "queue-throughput" => bench_queue_throughput().await,
+            unknown => {
+                eprintln!("  Unknown benchmark '{}', skipping", unknown);
+                continue;
+            }
+        };
+        report.print();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_report_stats() {
+        let report = BenchReport::new("test", vec![100, 200, 300], Some(1024));
+        assert_eq!(report.min_ns(), 100);
+        assert_eq!(report.median_ns(), 200);
+        assert_eq!(report.mean_ns(), 200.0);
+        assert!(report.mb_per_sec().is_some());
+    }
+}
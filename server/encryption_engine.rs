@@ -2,22 +2,68 @@
 pub use std::env;
 use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit, OsRng},
-    Aes256Gcm, Nonce, Key
+    Aes128Gcm, Aes256Gcm, Nonce, Key
 };
+use chacha20poly1305::ChaCha20Poly1305;
 use rsa::{RsaPrivateKey, RsaPublicKey, Oaep, sha2::Sha256};
 use rand::rngs::OsRng as RandOsRng;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::session_cache::{SessionCacheConfig, SessionStorage};
+
+/// The set of AEAD algorithms the engine can pick between. AES is only fast
+/// where hardware AES-NI is available; ChaCha20-Poly1305 wins everywhere
+/// else (including WASM), so the engine speed-tests candidates at startup
+/// rather than hardcoding one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CipherAlgorithm {
+    Aes256Gcm,
+    Aes128Gcm,
+    ChaCha20Poly1305,
+}
+
+impl CipherAlgorithm {
+    pub const ALL: [CipherAlgorithm; 3] = [
+        CipherAlgorithm::Aes256Gcm,
+        CipherAlgorithm::Aes128Gcm,
+        CipherAlgorithm::ChaCha20Poly1305,
+    ];
+
+    pub fn key_len(&self) -> usize {
+        match self {
+            CipherAlgorithm::Aes256Gcm => 32,
+            CipherAlgorithm::Aes128Gcm => 16,
+            CipherAlgorithm::ChaCha20Poly1305 => 32,
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EncryptedMessage {
     pub encrypted_content: String,
-    pub encrypted_symmetric_key: String,
+    // `None` when `resumed` is true: a resumed message derives its key from
+    // the cached session secret instead of an RSA-wrapped symmetric key.
+    pub encrypted_symmetric_key: Option<String>,
     pub nonce: String,
     pub timestamp: u64,
     pub message_id: String,
     pub version: String,
+    pub algorithm: CipherAlgorithm,
+    /// Whether this message's key was derived from a cached resumption
+    /// secret rather than a fresh RSA-4096 exchange.
+    pub resumed: bool,
+    /// Identifies which cached resumption secret to ratchet when `resumed`
+    /// is true. On a fresh RSA exchange it is `None`, except when
+    /// `resumable_encrypt_message` seeds the cache with that exchange, where
+    /// it names the newly cached secret.
+    pub session_id: Option<String>,
+    /// The ratchet position this message's key was derived at; `None` for a
+    /// fresh RSA exchange. Lets the receiver re-derive the same key without
+    /// keeping its own send-side ratchet position in sync.
+    pub resumption_counter: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -43,12 +89,152 @@ impl Error for EncryptionError {}
 
 pub struct RustEncryptionEngine {
     rsa_key_size: usize,
+    algorithm: CipherAlgorithm,
+    session_storage: SessionStorage,
 }
 
 impl RustEncryptionEngine {
     pub fn new() -> Self {
         Self {
             rsa_key_size: 4096, // Military-grade 4096-bit RSA
+            algorithm: Self::select_fastest(),
+            session_storage: SessionStorage::cache(SessionCacheConfig::default()),
+        }
+    }
+
+    // Construct an engine pinned to a specific AEAD algorithm, bypassing the
+    // startup speed test. Useful for tests or operators who already know
+    // which cipher their fleet should use.
+    pub fn with_algorithm(algorithm: CipherAlgorithm) -> Self {
+        Self {
+            rsa_key_size: 4096,
+            algorithm,
+            session_storage: SessionStorage::cache(SessionCacheConfig::default()),
+        }
+    }
+
+    // Construct an engine with an explicit session-resumption policy, e.g.
+    // `SessionStorage::NoSessionStorage` for forward-secrecy-sensitive
+    // deployments that must never skip the per-message RSA exchange.
+    pub fn with_session_storage(session_storage: SessionStorage) -> Self {
+        Self {
+            rsa_key_size: 4096,
+            algorithm: Self::select_fastest(),
+            session_storage,
+        }
+    }
+
+    pub fn algorithm(&self) -> CipherAlgorithm {
+        self.algorithm
+    }
+
+    // Speed-test each candidate AEAD algorithm by encrypting a fixed buffer
+    // in a tight loop for a short, fixed duration, then rank by measured
+    // throughput. This is run once at construction so the engine stays fast
+    // on both AES-NI and non-AES-NI targets instead of paying the AES
+    // penalty everywhere.
+    pub fn select_fastest() -> CipherAlgorithm {
+        const TEST_DURATION: Duration = Duration::from_millis(100);
+        const TEST_BUFFER_LEN: usize = 4096;
+
+        let test_buffer = vec![0x42u8; TEST_BUFFER_LEN];
+        let mut best: Option<(CipherAlgorithm, f64)> = None;
+
+        for candidate in CipherAlgorithm::ALL {
+            let throughput = Self::measure_throughput(candidate, &test_buffer, TEST_DURATION);
+            if best.map_or(true, |(_, best_throughput)| throughput > best_throughput) {
+                best = Some((candidate, throughput));
+            }
+        }
+
+        best.map(|(algorithm, _)| algorithm)
+            .unwrap_or(CipherAlgorithm::ChaCha20Poly1305)
+    }
+
+    fn measure_throughput(algorithm: CipherAlgorithm, buffer: &[u8], duration: Duration) -> f64 {
+        let mut iterations: u64 = 0;
+        let start = Instant::now();
+
+        while start.elapsed() < duration {
+            let _ = Self::encrypt_with_algorithm(algorithm, buffer, &Self::fresh_key(algorithm));
+            iterations += 1;
+        }
+
+        let elapsed = start.elapsed().as_secs_f64();
+        if elapsed == 0.0 {
+            0.0
+        } else {
+            (iterations as f64 * buffer.len() as f64) / elapsed
+        }
+    }
+
+    fn fresh_key(algorithm: CipherAlgorithm) -> Vec<u8> {
+        let mut key = vec![0u8; algorithm.key_len()];
+        use rand::RngCore;
+        RandOsRng.fill_bytes(&mut key);
+        key
+    }
+
+    // Encrypt with whichever AEAD the caller asks for, returning the nonce
+    // alongside the ciphertext so both can be persisted on `EncryptedMessage`.
+    fn encrypt_with_algorithm(
+        algorithm: CipherAlgorithm,
+        plaintext: &[u8],
+        key: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), EncryptionError> {
+        match algorithm {
+            CipherAlgorithm::Aes256Gcm => {
+                let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+                let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+                let ciphertext = cipher
+                    .encrypt(&nonce, plaintext)
+                    .map_err(EncryptionError::AesError)?;
+                Ok((nonce.to_vec(), ciphertext))
+            }
+            CipherAlgorithm::Aes128Gcm => {
+                let cipher = Aes128Gcm::new(Key::<Aes128Gcm>::from_slice(key));
+                let nonce = Aes128Gcm::generate_nonce(&mut OsRng);
+                let ciphertext = cipher
+                    .encrypt(&nonce, plaintext)
+                    .map_err(EncryptionError::AesError)?;
+                Ok((nonce.to_vec(), ciphertext))
+            }
+            CipherAlgorithm::ChaCha20Poly1305 => {
+                let cipher = ChaCha20Poly1305::new(Key::<ChaCha20Poly1305>::from_slice(key));
+                let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let ciphertext = cipher
+                    .encrypt(&nonce, plaintext)
+                    .map_err(EncryptionError::AesError)?;
+                Ok((nonce.to_vec(), ciphertext))
+            }
+        }
+    }
+
+    fn decrypt_with_algorithm(
+        algorithm: CipherAlgorithm,
+        ciphertext: &[u8],
+        key: &[u8],
+        nonce: &[u8],
+    ) -> Result<Vec<u8>, EncryptionError> {
+        match algorithm {
+            CipherAlgorithm::Aes256Gcm => {
+                let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+                cipher
+                    .decrypt(Nonce::from_slice(nonce), ciphertext)
+                    .map_err(EncryptionError::AesError)
+            }
+            CipherAlgorithm::Aes128Gcm => {
+                let cipher = Aes128Gcm::new(Key::<Aes128Gcm>::from_slice(key));
+                cipher
+                    .decrypt(Nonce::from_slice(nonce), ciphertext)
+                    .map_err(EncryptionError::AesError)
+            }
+            CipherAlgorithm::ChaCha20Poly1305 => {
+                let cipher = ChaCha20Poly1305::new(Key::<ChaCha20Poly1305>::from_slice(key));
+                cipher
+                    .decrypt(Nonce::from_slice(nonce), ciphertext)
+                    .map_err(EncryptionError::AesError)
+            }
         }
     }
 
@@ -61,37 +247,55 @@ impl RustEncryptionEngine {
         Ok((private_key, public_key))
     }
 
-    // Hybrid encryption: AES-256-GCM + RSA-4096
+    // Hybrid encryption: speed-selected AEAD + RSA-4096
     pub fn encrypt_message(
         &self,
         message: &str,
         rsa_public_key: &RsaPublicKey,
     ) -> Result<EncryptedMessage, EncryptionError> {
-        // Generate random AES-256 key
-        let aes_key = Aes256Gcm::generate_key(OsRng);
-        let cipher = Aes256Gcm::new(&aes_key);
+        self.encrypt_message_with(message, rsa_public_key, self.algorithm)
+    }
+
+    // Same as `encrypt_message` but lets the caller override the
+    // auto-selected algorithm for this particular message.
+    pub fn encrypt_message_with(
+        &self,
+        message: &str,
+        rsa_public_key: &RsaPublicKey,
+        algorithm: CipherAlgorithm,
+    ) -> Result<EncryptedMessage, EncryptionError> {
+        self.encrypt_message_with_exposed_key(message, rsa_public_key, algorithm)
+            .map(|(encrypted, _symmetric_key)| encrypted)
+    }
 
-        // Generate random nonce
-        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    // Same as `encrypt_message_with` but also returns the raw symmetric key
+    // so callers establishing a resumable session can seed the cache with it.
+    fn encrypt_message_with_exposed_key(
+        &self,
+        message: &str,
+        rsa_public_key: &RsaPublicKey,
+        algorithm: CipherAlgorithm,
+    ) -> Result<(EncryptedMessage, Vec<u8>), EncryptionError> {
+        // Generate a random symmetric key sized for the chosen algorithm
+        let symmetric_key = Self::fresh_key(algorithm);
 
-        // Encrypt message with AES-256-GCM
-        let encrypted_content = cipher
-            .encrypt(&nonce, message.as_bytes())
-            .map_err(EncryptionError::AesError)?;
+        // Encrypt message with the chosen AEAD algorithm
+        let (nonce, encrypted_content) =
+            Self::encrypt_with_algorithm(algorithm, message.as_bytes(), &symmetric_key)?;
 
-        // Encrypt AES key with RSA-4096
+        // Encrypt the symmetric key with RSA-4096
         let mut rng = RandOsRng;
         let padding = Oaep::new::<Sha256>();
         let encrypted_symmetric_key = rsa_public_key
-            .encrypt(&mut rng, padding, &aes_key)
+            .encrypt(&mut rng, padding, &symmetric_key)
             .map_err(EncryptionError::RsaError)?;
 
         // Generate secure message ID
         let message_id = self.generate_secure_id();
 
-        Ok(EncryptedMessage {
+        let encrypted = EncryptedMessage {
             encrypted_content: base64::encode(encrypted_content),
-            encrypted_symmetric_key: base64::encode(encrypted_symmetric_key),
+            encrypted_symmetric_key: Some(base64::encode(encrypted_symmetric_key)),
             nonce: base64::encode(nonce),
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
@@ -99,10 +303,16 @@ impl RustEncryptionEngine {
                 .as_secs(),
             message_id,
             version: "3.0-rust".to_string(),
-        })
+            algorithm,
+            resumed: false,
+            session_id: None,
+            resumption_counter: None,
+        };
+        Ok((encrypted, symmetric_key))
     }
 
-    // Hybrid decryption
+    // Hybrid decryption, dispatching to whichever AEAD algorithm the
+    // message was sealed with
     pub fn decrypt_message(
         &self,
         encrypted_msg: &EncryptedMessage,
@@ -111,33 +321,179 @@ impl RustEncryptionEngine {
         // Decode base64 data
         let encrypted_content = base64::decode(&encrypted_msg.encrypted_content)
             .map_err(EncryptionError::Base64Error)?;
-        let encrypted_symmetric_key = base64::decode(&encrypted_msg.encrypted_symmetric_key)
-            .map_err(EncryptionError::Base64Error)?;
+        let encrypted_symmetric_key = encrypted_msg
+            .encrypted_symmetric_key
+            .as_ref()
+            .ok_or_else(|| {
+                EncryptionError::InvalidInput(
+                    "message has no RSA-wrapped symmetric key; use resumable_decrypt_message for resumed sessions".to_string(),
+                )
+            })
+            .and_then(|key| base64::decode(key).map_err(EncryptionError::Base64Error))?;
         let nonce_bytes = base64::decode(&encrypted_msg.nonce)
             .map_err(EncryptionError::Base64Error)?;
 
-        // Decrypt AES key with RSA
+        // Decrypt the symmetric key with RSA
         let padding = Oaep::new::<Sha256>();
-        let aes_key_bytes = rsa_private_key
+        let symmetric_key_bytes = rsa_private_key
             .decrypt(padding, &encrypted_symmetric_key)
             .map_err(EncryptionError::RsaError)?;
 
-        // Reconstruct AES key
-        let aes_key = Key::<Aes256Gcm>::from_slice(&aes_key_bytes);
-        let cipher = Aes256Gcm::new(aes_key);
+        // Decrypt message with whichever algorithm it was sealed with
+        let decrypted_content = Self::decrypt_with_algorithm(
+            encrypted_msg.algorithm,
+            &encrypted_content,
+            &symmetric_key_bytes,
+            &nonce_bytes,
+        )?;
 
-        // Reconstruct nonce
-        let nonce = Nonce::from_slice(&nonce_bytes);
+        String::from_utf8(decrypted_content)
+            .map_err(|e| EncryptionError::InvalidInput(format!("Invalid UTF-8: {}", e)))
+    }
 
-        // Decrypt message
-        let decrypted_content = cipher
-            .decrypt(nonce, encrypted_content.as_ref())
-            .map_err(EncryptionError::AesError)?;
+    /// Encrypt a message to `peer_id`, skipping the RSA-4096 exchange when a
+    /// resumption secret from an earlier exchange with that peer is still
+    /// cached: the per-message key is instead derived from the cached secret
+    /// via an HKDF ratchet. Falls back to a full RSA exchange (and, for
+    /// `SessionStorage::Cache`, seeds the cache for next time) otherwise.
+    pub fn resumable_encrypt_message(
+        &self,
+        message: &str,
+        peer_id: &str,
+        rsa_public_key: &RsaPublicKey,
+    ) -> Result<EncryptedMessage, EncryptionError> {
+        let cache = match &self.session_storage {
+            SessionStorage::Cache(cache) => cache,
+            SessionStorage::NoSessionStorage => {
+                return self.encrypt_message(message, rsa_public_key)
+            }
+        };
+
+        let ratcheted = cache.write().unwrap().next_key(peer_id);
+        if let Some((ratchet_key, session_id, counter)) = ratcheted {
+            return self.seal_resumed(message, &ratchet_key, session_id, counter);
+        }
+
+        let (mut encrypted, symmetric_key) =
+            self.encrypt_message_with_exposed_key(message, rsa_public_key, self.algorithm)?;
+
+        let resumption_secret: [u8; 32] = blake3::hash(&symmetric_key).into();
+        let session_id = cache.write().unwrap().insert(peer_id, resumption_secret);
+        encrypted.session_id = Some(session_id);
+        Ok(encrypted)
+    }
+
+    // Seal `message` under a key derived from an already-ratcheted
+    // resumption secret, marking the result as resumed and carrying the
+    // ratchet position so the receiver can re-derive the same key.
+    fn seal_resumed(
+        &self,
+        message: &str,
+        ratchet_key: &[u8; 32],
+        session_id: String,
+        counter: u64,
+    ) -> Result<EncryptedMessage, EncryptionError> {
+        let symmetric_key = Self::expand_to_key_len(ratchet_key, self.algorithm.key_len());
+        let (nonce, encrypted_content) =
+            Self::encrypt_with_algorithm(self.algorithm, message.as_bytes(), &symmetric_key)?;
+
+        Ok(EncryptedMessage {
+            encrypted_content: base64::encode(encrypted_content),
+            encrypted_symmetric_key: None,
+            nonce: base64::encode(nonce),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            message_id: self.generate_secure_id(),
+            version: "3.0-rust".to_string(),
+            algorithm: self.algorithm,
+            resumed: true,
+            session_id: Some(session_id),
+            resumption_counter: Some(counter),
+        })
+    }
+
+    /// Decrypt a message from `peer_id`, pulling the per-message key from
+    /// the resumption cache when `encrypted_msg.resumed` is set, or running
+    /// the RSA path (and seeding the cache) otherwise.
+    pub fn resumable_decrypt_message(
+        &self,
+        encrypted_msg: &EncryptedMessage,
+        peer_id: &str,
+        rsa_private_key: &RsaPrivateKey,
+    ) -> Result<String, EncryptionError> {
+        if !encrypted_msg.resumed {
+            let plaintext = self.decrypt_message(encrypted_msg, rsa_private_key)?;
+
+            if let SessionStorage::Cache(cache) = &self.session_storage {
+                if let Some(encoded_key) = &encrypted_msg.encrypted_symmetric_key {
+                    let encrypted_symmetric_key = base64::decode(encoded_key)
+                        .map_err(EncryptionError::Base64Error)?;
+                    let padding = Oaep::new::<Sha256>();
+                    let symmetric_key_bytes = rsa_private_key
+                        .decrypt(padding, &encrypted_symmetric_key)
+                        .map_err(EncryptionError::RsaError)?;
+                    let resumption_secret: [u8; 32] = blake3::hash(&symmetric_key_bytes).into();
+                    cache.write().unwrap().insert(peer_id, resumption_secret);
+                }
+            }
+
+            return Ok(plaintext);
+        }
+
+        let cache = match &self.session_storage {
+            SessionStorage::Cache(cache) => cache,
+            SessionStorage::NoSessionStorage => {
+                return Err(EncryptionError::InvalidInput(
+                    "received a resumed message but this engine has no session storage configured"
+                        .to_string(),
+                ))
+            }
+        };
+        let session_id = encrypted_msg.session_id.as_deref().ok_or_else(|| {
+            EncryptionError::InvalidInput("resumed message is missing a session_id".to_string())
+        })?;
+        let counter = encrypted_msg.resumption_counter.ok_or_else(|| {
+            EncryptionError::InvalidInput("resumed message is missing a resumption_counter".to_string())
+        })?;
+
+        let ratchet_key = cache
+            .write()
+            .unwrap()
+            .key_for_counter(peer_id, session_id, counter)
+            .ok_or_else(|| {
+                EncryptionError::InvalidInput(
+                    "resumption secret expired, unknown session, or counter already used".to_string(),
+                )
+            })?;
+
+        let symmetric_key = Self::expand_to_key_len(&ratchet_key, encrypted_msg.algorithm.key_len());
+        let encrypted_content = base64::decode(&encrypted_msg.encrypted_content)
+            .map_err(EncryptionError::Base64Error)?;
+        let nonce_bytes = base64::decode(&encrypted_msg.nonce)
+            .map_err(EncryptionError::Base64Error)?;
+
+        let decrypted_content = Self::decrypt_with_algorithm(
+            encrypted_msg.algorithm,
+            &encrypted_content,
+            &symmetric_key,
+            &nonce_bytes,
+        )?;
 
         String::from_utf8(decrypted_content)
             .map_err(|e| EncryptionError::InvalidInput(format!("Invalid UTF-8: {}", e)))
     }
 
+    // Expand a 32-byte ratchet output into a key sized for the AEAD in use
+    // (AES-128-GCM needs 16 bytes; the others need 32).
+    fn expand_to_key_len(ratchet_key: &[u8; 32], key_len: usize) -> Vec<u8> {
+        if key_len == ratchet_key.len() {
+            return ratchet_key.to_vec();
+        }
+        ratchet_key[..key_len].to_vec()
+    }
+
     // Generate cryptographically secure ID
     fn generate_secure_id(&self) -> String {
         use rand::Rng;
@@ -165,6 +521,7 @@ impl RustEncryptionEngine {
         let decrypt_time = start.elapsed();
 
         println!("ðŸ¦€ Rust Encryption Benchmark:");
+        println!("  Algorithm: {:?}", self.algorithm);
         println!("  Encryption: {:?}", encrypt_time);
         println!("  Decryption: {:?}", decrypt_time);
         println!("  Total: {:?}", encrypt_time + decrypt_time);
@@ -207,4 +564,69 @@ mod tests {
         let engine = RustEncryptionEngine::new();
         engine.benchmark().unwrap();
     }
+
+    #[test]
+    fn test_encrypt_decrypt_with_every_algorithm() {
+        let engine = RustEncryptionEngine::new();
+        let (private_key, public_key) = engine.generate_rsa_keypair().unwrap();
+        let message = "Cipher-agile test message";
+
+        for algorithm in CipherAlgorithm::ALL {
+            let encrypted = engine
+                .encrypt_message_with(message, &public_key, algorithm)
+                .unwrap();
+            assert_eq!(encrypted.algorithm, algorithm);
+
+            let decrypted = engine.decrypt_message(&encrypted, &private_key).unwrap();
+            assert_eq!(message, decrypted);
+        }
+    }
+
+    #[test]
+    fn test_resumable_messages_skip_rsa_after_first_exchange() {
+        let engine = RustEncryptionEngine::new();
+        let (private_key, public_key) = engine.generate_rsa_keypair().unwrap();
+        let peer_id = "peer-42";
+
+        let first = engine
+            .resumable_encrypt_message("first message", peer_id, &public_key)
+            .unwrap();
+        assert!(!first.resumed);
+        assert!(first.encrypted_symmetric_key.is_some());
+
+        let decrypted_first = engine
+            .resumable_decrypt_message(&first, peer_id, &private_key)
+            .unwrap();
+        assert_eq!(decrypted_first, "first message");
+
+        let second = engine
+            .resumable_encrypt_message("second message", peer_id, &public_key)
+            .unwrap();
+        assert!(second.resumed);
+        assert!(second.encrypted_symmetric_key.is_none());
+
+        let decrypted_second = engine
+            .resumable_decrypt_message(&second, peer_id, &private_key)
+            .unwrap();
+        assert_eq!(decrypted_second, "second message");
+    }
+
+    #[test]
+    fn test_no_session_storage_always_uses_rsa() {
+        let engine = RustEncryptionEngine::with_session_storage(
+            crate::session_cache::SessionStorage::NoSessionStorage,
+        );
+        let (private_key, public_key) = engine.generate_rsa_keypair().unwrap();
+        let peer_id = "peer-7";
+
+        let first = engine
+            .resumable_encrypt_message("hello", peer_id, &public_key)
+            .unwrap();
+        let second = engine
+            .resumable_encrypt_message("world", peer_id, &public_key)
+            .unwrap();
+
+        assert!(!first.resumed);
+        assert!(!second.resumed);
+    }
 }
@@ -0,0 +1,509 @@
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::Argon2;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hkdf::Hkdf;
+use rand::rngs::OsRng as RandOsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey};
+
+// Size of the sliding window used to reject replayed message counters.
+const REPLAY_WINDOW_SIZE: u64 = 1024;
+
+#[derive(Debug)]
+pub enum HandshakeError {
+    UntrustedPeer,
+    InvalidSignature,
+    NotEstablished,
+    ReplayedCounter(u64),
+    AesError(aes_gcm::Error),
+    InvalidInput(String),
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HandshakeError::UntrustedPeer => write!(f, "peer identity key is not trusted"),
+            HandshakeError::InvalidSignature => write!(f, "handshake signature verification failed"),
+            HandshakeError::NotEstablished => write!(f, "session is not established yet"),
+            HandshakeError::ReplayedCounter(c) => write!(f, "rejected replayed counter: {}", c),
+            HandshakeError::AesError(e) => write!(f, "AES error: {}", e),
+            HandshakeError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+        }
+    }
+}
+
+impl Error for HandshakeError {}
+
+/// How a node's Ed25519 identity key is obtained and how peers are trusted.
+pub enum TrustMode {
+    /// All nodes derive the same identity keypair from a shared passphrase,
+    /// so the common public key is implicitly trusted.
+    SharedSecret { passphrase: String },
+    /// The identity keypair is generated randomly; peer public keys must be
+    /// added to an explicit allow-list before a handshake with them succeeds.
+    ExplicitTrust,
+}
+
+/// Tunable policy controlling when `Session` ratchets its chain key.
+#[derive(Debug, Clone, Copy)]
+pub struct RekeyPolicy {
+    pub max_messages: u64,
+    pub max_interval: Duration,
+}
+
+impl Default for RekeyPolicy {
+    fn default() -> Self {
+        Self {
+            max_messages: 10_000,
+            max_interval: Duration::from_secs(3600),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HandshakeMessage {
+    pub identity_public_key: [u8; 32],
+    pub ephemeral_public_key: [u8; 32],
+    // serde only has built-in (de)serialize impls for fixed arrays up to
+    // length 32, so the 64-byte Ed25519 signature is carried as a Vec
+    // instead; `verify_peer` checks its length before use.
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SealedMessage {
+    pub counter: u64,
+    pub ciphertext: String,
+}
+
+struct ReplayWindow {
+    highest_seen: Option<u64>,
+    seen: HashSet<u64>,
+}
+
+impl ReplayWindow {
+    fn new() -> Self {
+        Self {
+            highest_seen: None,
+            seen: HashSet::new(),
+        }
+    }
+
+    // Accept a counter unless it falls outside the sliding window or has
+    // already been seen within it.
+    fn check_and_record(&mut self, counter: u64) -> Result<(), HandshakeError> {
+        if let Some(highest) = self.highest_seen {
+            // `counter` comes straight off the wire, unverified; a
+            // maliciously large value must not be able to overflow this.
+            if counter.saturating_add(REPLAY_WINDOW_SIZE) <= highest {
+                return Err(HandshakeError::ReplayedCounter(counter));
+            }
+        }
+        if !self.seen.insert(counter) {
+            return Err(HandshakeError::ReplayedCounter(counter));
+        }
+
+        let highest = self.highest_seen.map_or(counter, |h| h.max(counter));
+        self.highest_seen = Some(highest);
+        self.seen
+            .retain(|c| c.saturating_add(REPLAY_WINDOW_SIZE) > highest);
+        Ok(())
+    }
+}
+
+/// A Noise-inspired authenticated session between two peers: an X25519 ECDH
+/// establishes the shared secret, an Ed25519 signature over the ephemeral
+/// key authenticates each side against the other's trusted key set, and
+/// HKDF-SHA256 derives directional AES-256-GCM keys that are periodically
+/// ratcheted forward.
+pub struct Session {
+    identity_signing_key: SigningKey,
+    trusted_keys: HashSet<[u8; 32]>,
+    local_ephemeral: Option<EphemeralSecret>,
+    send_chain_key: Option<[u8; 32]>,
+    recv_chain_key: Option<[u8; 32]>,
+    send_counter: u64,
+    // Rekeying is driven independently per direction: our send count has no
+    // relationship to how many messages we've received, so a single shared
+    // counter would leave one chain key ratcheting out of step with the peer.
+    send_messages_since_rekey: u64,
+    recv_messages_since_rekey: u64,
+    last_send_rekey: Instant,
+    last_recv_rekey: Instant,
+    rekey_policy: RekeyPolicy,
+    replay_window: ReplayWindow,
+}
+
+impl Session {
+    pub fn new(trust_mode: TrustMode, rekey_policy: RekeyPolicy) -> Result<Self, HandshakeError> {
+        let identity_signing_key = match &trust_mode {
+            TrustMode::SharedSecret { passphrase } => Self::derive_identity_key(passphrase)?,
+            TrustMode::ExplicitTrust => SigningKey::generate(&mut RandOsRng),
+        };
+
+        let mut trusted_keys = HashSet::new();
+        if let TrustMode::SharedSecret { .. } = &trust_mode {
+            trusted_keys.insert(identity_signing_key.verifying_key().to_bytes());
+        }
+
+        Ok(Self {
+            identity_signing_key,
+            trusted_keys,
+            local_ephemeral: None,
+            send_chain_key: None,
+            recv_chain_key: None,
+            send_counter: 0,
+            send_messages_since_rekey: 0,
+            recv_messages_since_rekey: 0,
+            last_send_rekey: Instant::now(),
+            last_recv_rekey: Instant::now(),
+            rekey_policy,
+            replay_window: ReplayWindow::new(),
+        })
+    }
+
+    /// Add a peer's Ed25519 public key to the trusted set (explicit-trust mode).
+    pub fn trust_peer(&mut self, identity_public_key: [u8; 32]) {
+        self.trusted_keys.insert(identity_public_key);
+    }
+
+    // Deterministically derive an Ed25519 identity key from a passphrase
+    // via Argon2 so that every node sharing the passphrase ends up with the
+    // same keypair (and therefore the same trusted public key).
+    fn derive_identity_key(passphrase: &str) -> Result<SigningKey, HandshakeError> {
+        let mut seed = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), b"ulta-secure-messenger-handshake", &mut seed)
+            .map_err(|e| HandshakeError::InvalidInput(format!("key derivation failed: {}", e)))?;
+        Ok(SigningKey::from_bytes(&seed))
+    }
+
+    /// Start a handshake: generate an ephemeral X25519 keypair, sign it with
+    /// our Ed25519 identity key, and return the message to send to the peer.
+    pub fn begin_handshake(&mut self) -> HandshakeMessage {
+        let ephemeral = EphemeralSecret::random_from_rng(RandOsRng);
+        let ephemeral_public_key = X25519PublicKey::from(&ephemeral).to_bytes();
+        let signature: Signature = self.identity_signing_key.sign(&ephemeral_public_key);
+
+        self.local_ephemeral = Some(ephemeral);
+
+        HandshakeMessage {
+            identity_public_key: self.identity_signing_key.verifying_key().to_bytes(),
+            ephemeral_public_key,
+            signature: signature.to_bytes().to_vec(),
+        }
+    }
+
+    /// Respond to an initiator's handshake message: verify it against the
+    /// trusted key set, generate our own ephemeral keypair, derive the
+    /// directional session keys, and return our reply message.
+    pub fn accept_handshake(
+        &mut self,
+        peer_msg: &HandshakeMessage,
+    ) -> Result<HandshakeMessage, HandshakeError> {
+        self.verify_peer(peer_msg)?;
+
+        let ephemeral = EphemeralSecret::random_from_rng(RandOsRng);
+        let ephemeral_public_key = X25519PublicKey::from(&ephemeral).to_bytes();
+        let signature: Signature = self.identity_signing_key.sign(&ephemeral_public_key);
+
+        let peer_ephemeral = X25519PublicKey::from(peer_msg.ephemeral_public_key);
+        let shared_secret = ephemeral.diffie_hellman(&peer_ephemeral);
+        // We are the responder: our send direction is the peer's receive direction.
+        self.derive_session_keys(shared_secret.as_bytes(), false)?;
+
+        Ok(HandshakeMessage {
+            identity_public_key: self.identity_signing_key.verifying_key().to_bytes(),
+            ephemeral_public_key,
+            signature: signature.to_bytes().to_vec(),
+        })
+    }
+
+    /// Called by the initiator once the responder's reply arrives: verify it
+    /// and derive the same directional session keys from the shared secret.
+    pub fn complete_handshake(&mut self, peer_msg: &HandshakeMessage) -> Result<(), HandshakeError> {
+        self.verify_peer(peer_msg)?;
+
+        let ephemeral = self
+            .local_ephemeral
+            .take()
+            .ok_or(HandshakeError::NotEstablished)?;
+        let peer_ephemeral = X25519PublicKey::from(peer_msg.ephemeral_public_key);
+        let shared_secret = ephemeral.diffie_hellman(&peer_ephemeral);
+        self.derive_session_keys(shared_secret.as_bytes(), true)
+    }
+
+    fn verify_peer(&self, peer_msg: &HandshakeMessage) -> Result<(), HandshakeError> {
+        if !self.trusted_keys.contains(&peer_msg.identity_public_key) {
+            return Err(HandshakeError::UntrustedPeer);
+        }
+        let verifying_key = VerifyingKey::from_bytes(&peer_msg.identity_public_key)
+            .map_err(|_| HandshakeError::InvalidSignature)?;
+        let signature_bytes: [u8; 64] = peer_msg
+            .signature
+            .as_slice()
+            .try_into()
+            .map_err(|_| HandshakeError::InvalidSignature)?;
+        let signature = Signature::from_bytes(&signature_bytes);
+        verifying_key
+            .verify(&peer_msg.ephemeral_public_key, &signature)
+            .map_err(|_| HandshakeError::InvalidSignature)
+    }
+
+    // HKDF-SHA256 over the ECDH shared secret yields two directional chain
+    // keys; `is_initiator` decides which one is ours to send with.
+    fn derive_session_keys(&mut self, shared_secret: &[u8], is_initiator: bool) -> Result<(), HandshakeError> {
+        let hkdf = Hkdf::<Sha256>::new(None, shared_secret);
+        let mut initiator_key = [0u8; 32];
+        let mut responder_key = [0u8; 32];
+        hkdf.expand(b"ulta-secure-messenger initiator", &mut initiator_key)
+            .map_err(|_| HandshakeError::InvalidInput("HKDF expand failed".to_string()))?;
+        hkdf.expand(b"ulta-secure-messenger responder", &mut responder_key)
+            .map_err(|_| HandshakeError::InvalidInput("HKDF expand failed".to_string()))?;
+
+        let (send, recv) = if is_initiator {
+            (initiator_key, responder_key)
+        } else {
+            (responder_key, initiator_key)
+        };
+
+        self.send_chain_key = Some(send);
+        self.recv_chain_key = Some(recv);
+        self.send_counter = 0;
+        self.send_messages_since_rekey = 0;
+        self.recv_messages_since_rekey = 0;
+        self.last_send_rekey = Instant::now();
+        self.last_recv_rekey = Instant::now();
+        Ok(())
+    }
+
+    // Ratchet a chain key forward with HKDF so past keys cannot be recovered
+    // from the current one.
+    fn ratchet(chain_key: &[u8; 32]) -> Result<[u8; 32], HandshakeError> {
+        let hkdf = Hkdf::<Sha256>::new(None, chain_key);
+        let mut next = [0u8; 32];
+        hkdf.expand(b"ulta-secure-messenger ratchet", &mut next)
+            .map_err(|_| HandshakeError::InvalidInput("HKDF ratchet failed".to_string()))?;
+        Ok(next)
+    }
+
+    // Ratchet the send chain key once our own send count/time crosses the
+    // policy threshold. Driven only by messages *we* have sent, so it stays
+    // in step with the peer's matching `maybe_rekey_recv` regardless of how
+    // much traffic is flowing the other way.
+    fn maybe_rekey_send(&mut self) -> Result<(), HandshakeError> {
+        let should_rekey = self.send_messages_since_rekey >= self.rekey_policy.max_messages
+            || self.last_send_rekey.elapsed() >= self.rekey_policy.max_interval;
+        if !should_rekey {
+            return Ok(());
+        }
+
+        if let Some(key) = &self.send_chain_key {
+            self.send_chain_key = Some(Self::ratchet(key)?);
+        }
+        self.send_messages_since_rekey = 0;
+        self.last_send_rekey = Instant::now();
+        Ok(())
+    }
+
+    // Ratchet the recv chain key once our own receive count/time crosses the
+    // policy threshold, mirroring the peer's `maybe_rekey_send`.
+    fn maybe_rekey_recv(&mut self) -> Result<(), HandshakeError> {
+        let should_rekey = self.recv_messages_since_rekey >= self.rekey_policy.max_messages
+            || self.last_recv_rekey.elapsed() >= self.rekey_policy.max_interval;
+        if !should_rekey {
+            return Ok(());
+        }
+
+        if let Some(key) = &self.recv_chain_key {
+            self.recv_chain_key = Some(Self::ratchet(key)?);
+        }
+        self.recv_messages_since_rekey = 0;
+        self.last_recv_rekey = Instant::now();
+        Ok(())
+    }
+
+    /// Encrypt `plaintext` under the send chain key, using the per-message
+    /// counter as the GCM nonce so out-of-order delivery is tolerated.
+    pub fn seal(&mut self, plaintext: &[u8]) -> Result<SealedMessage, HandshakeError> {
+        self.maybe_rekey_send()?;
+
+        let chain_key = self.send_chain_key.ok_or(HandshakeError::NotEstablished)?;
+        let key = Key::<Aes256Gcm>::from_slice(&chain_key);
+        let cipher = Aes256Gcm::new(key);
+
+        let counter = self.send_counter;
+        let nonce_bytes = Self::nonce_for_counter(counter);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+        let ciphertext = cipher
+            .encrypt(nonce, plaintext)
+            .map_err(HandshakeError::AesError)?;
+
+        self.send_counter += 1;
+        self.send_messages_since_rekey += 1;
+
+        Ok(SealedMessage {
+            counter,
+            ciphertext: base64::encode(ciphertext),
+        })
+    }
+
+    /// Decrypt a `SealedMessage` received from the peer, rejecting counters
+    /// already seen within the replay window.
+    pub fn open(&mut self, sealed: &SealedMessage) -> Result<Vec<u8>, HandshakeError> {
+        self.replay_window.check_and_record(sealed.counter)?;
+        self.maybe_rekey_recv()?;
+
+        let chain_key = self.recv_chain_key.ok_or(HandshakeError::NotEstablished)?;
+        let key = Key::<Aes256Gcm>::from_slice(&chain_key);
+        let cipher = Aes256Gcm::new(key);
+
+        let nonce_bytes = Self::nonce_for_counter(sealed.counter);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+        let ciphertext = base64::decode(&sealed.ciphertext)
+            .map_err(|e| HandshakeError::InvalidInput(format!("Invalid base64: {}", e)))?;
+
+        self.recv_messages_since_rekey += 1;
+        cipher
+            .decrypt(nonce, ciphertext.as_ref())
+            .map_err(HandshakeError::AesError)
+    }
+
+    // The 96-bit GCM nonce is the 64-bit counter zero-padded, matching the
+    // "explicit per-message counter as nonce" scheme described for sessions.
+    fn nonce_for_counter(counter: u64) -> [u8; 12] {
+        let mut bytes = [0u8; 12];
+        bytes[4..].copy_from_slice(&counter.to_be_bytes());
+        bytes
+    }
+}
+
+// Generate a random 32-byte seed, used by callers that want a fresh
+// passphrase-independent salt for explicit-trust deployments.
+pub fn generate_random_seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    RandOsRng.fill_bytes(&mut seed);
+    seed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_secret_mode_agrees_on_trusted_key() {
+        let a = Session::new(
+            TrustMode::SharedSecret { passphrase: "correct horse battery staple".to_string() },
+            RekeyPolicy::default(),
+        )
+        .unwrap();
+        let b = Session::new(
+            TrustMode::SharedSecret { passphrase: "correct horse battery staple".to_string() },
+            RekeyPolicy::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            a.identity_signing_key.verifying_key().to_bytes(),
+            b.identity_signing_key.verifying_key().to_bytes()
+        );
+    }
+
+    #[test]
+    fn test_handshake_and_seal_open_roundtrip() {
+        let mut initiator = Session::new(TrustMode::ExplicitTrust, RekeyPolicy::default()).unwrap();
+        let mut responder = Session::new(TrustMode::ExplicitTrust, RekeyPolicy::default()).unwrap();
+
+        initiator.trust_peer(responder.identity_signing_key.verifying_key().to_bytes());
+        responder.trust_peer(initiator.identity_signing_key.verifying_key().to_bytes());
+
+        let init_msg = initiator.begin_handshake();
+        let response_msg = responder.accept_handshake(&init_msg).unwrap();
+        initiator.complete_handshake(&response_msg).unwrap();
+
+        let sealed = initiator.seal(b"hello peer").unwrap();
+        let opened = responder.open(&sealed).unwrap();
+        assert_eq!(opened, b"hello peer");
+    }
+
+    #[test]
+    fn test_replay_is_rejected() {
+        let mut initiator = Session::new(TrustMode::ExplicitTrust, RekeyPolicy::default()).unwrap();
+        let mut responder = Session::new(TrustMode::ExplicitTrust, RekeyPolicy::default()).unwrap();
+
+        initiator.trust_peer(responder.identity_signing_key.verifying_key().to_bytes());
+        responder.trust_peer(initiator.identity_signing_key.verifying_key().to_bytes());
+
+        let init_msg = initiator.begin_handshake();
+        let response_msg = responder.accept_handshake(&init_msg).unwrap();
+        initiator.complete_handshake(&response_msg).unwrap();
+
+        let sealed = initiator.seal(b"hello").unwrap();
+        responder.open(&sealed).unwrap();
+
+        assert!(matches!(
+            responder.open(&sealed),
+            Err(HandshakeError::ReplayedCounter(_))
+        ));
+    }
+
+    #[test]
+    fn test_rekey_stays_in_step_with_one_sided_traffic() {
+        // The initiator sends many messages while the responder never sends
+        // any back; only the initiator's send-side threshold is ever
+        // crossed, but the responder must still ratchet its recv chain key
+        // in step purely from receiving, or later messages fail to open.
+        let policy = RekeyPolicy {
+            max_messages: 3,
+            max_interval: Duration::from_secs(3600),
+        };
+        let mut initiator = Session::new(TrustMode::ExplicitTrust, policy).unwrap();
+        let mut responder = Session::new(TrustMode::ExplicitTrust, policy).unwrap();
+
+        initiator.trust_peer(responder.identity_signing_key.verifying_key().to_bytes());
+        responder.trust_peer(initiator.identity_signing_key.verifying_key().to_bytes());
+
+        let init_msg = initiator.begin_handshake();
+        let response_msg = responder.accept_handshake(&init_msg).unwrap();
+        initiator.complete_handshake(&response_msg).unwrap();
+
+        for i in 0..10 {
+            let sealed = initiator.seal(format!("message {}", i).as_bytes()).unwrap();
+            let opened = responder.open(&sealed).unwrap();
+            assert_eq!(opened, format!("message {}", i).as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_replay_window_rejects_near_max_counter_without_overflow() {
+        let mut window = ReplayWindow::new();
+        window.check_and_record(u64::MAX).unwrap();
+
+        // A lower, unseen counter must still be accepted or rejected on its
+        // merits rather than panicking/wrapping on the window-bound add.
+        let result = window.check_and_record(0);
+        assert!(matches!(result, Err(HandshakeError::ReplayedCounter(0))));
+    }
+
+    #[test]
+    fn test_untrusted_peer_is_rejected() {
+        let mut initiator = Session::new(TrustMode::ExplicitTrust, RekeyPolicy::default()).unwrap();
+        let mut responder = Session::new(TrustMode::ExplicitTrust, RekeyPolicy::default()).unwrap();
+
+        let init_msg = initiator.begin_handshake();
+        assert!(matches!(
+            responder.accept_handshake(&init_msg),
+            Err(HandshakeError::UntrustedPeer)
+        ));
+    }
+}
@@ -1,8 +1,12 @@
 
 use std::env;
 
+mod attachment;
+mod bench;
 mod encryption_engine;
+mod handshake;
 mod message_processor;
+mod session_cache;
 
 use encryption_engine::RustEncryptionEngine;
 use message_processor::RustMessageProcessor;
@@ -34,10 +38,10 @@ async fn main() {
             }
         },
         "benchmark" => {
-            let engine = RustEncryptionEngine::new();
-            if let Err(e) = engine.benchmark() {
-                eprintln!("Benchmark failed: {}", e);
-            }
+            // Pass any trailing args through as the set of named benchmarks
+            // to run; running with no names runs the full suite.
+            let selected: Vec<String> = args[2..].to_vec();
+            bench::run(&selected).await;
         },
         "metrics" => {
             println!(r#"{{"rust_version":"1.75","memory_usage":"12MB","performance":"optimal","status":"healthy"}}"#);
@@ -1,4 +1,5 @@
 
+use crate::attachment::{self, EncryptedAttachment};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use tokio::sync::mpsc;
@@ -13,6 +14,9 @@ pub struct Message {
     pub content: String,
     pub timestamp: u64,
     pub message_type: MessageType,
+    // Present for Image/File/Voice/Video messages; the caller-supplied key
+    // that produced it is never stored alongside it.
+    pub attachment: Option<EncryptedAttachment>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +59,29 @@ impl RustMessageProcessor {
         }
     }
 
+    /// Encrypt a binary attachment for `message_id` under a caller-supplied
+    /// 256-bit key. The key is never stored by the processor; only the
+    /// resulting ciphertext frames and a fingerprint of the key are.
+    pub fn encrypt_attachment(
+        &self,
+        message_id: &str,
+        key: &[u8],
+        data: &[u8],
+    ) -> Result<EncryptedAttachment, String> {
+        attachment::encrypt_attachment(key, message_id, data).map_err(|e| e.to_string())
+    }
+
+    /// Decrypt a message's attachment, rejecting the request if `key`'s
+    /// fingerprint does not match the one it was encrypted with.
+    pub fn decrypt_attachment(
+        &self,
+        message_id: &str,
+        key: &[u8],
+        encrypted: &EncryptedAttachment,
+    ) -> Result<Vec<u8>, String> {
+        attachment::decrypt_attachment(key, message_id, encrypted).map_err(|e| e.to_string())
+    }
+
     // High-performance message queueing
     pub async fn queue_message(&self, message: Message) -> Result<(), String> {
         let start_time = Instant::now();
@@ -103,28 +130,75 @@ impl RustMessageProcessor {
     // Message validation and processing
     async fn process_messages(&self, messages: Vec<Message>) -> Result<Vec<Message>, String> {
         let mut processed = Vec::with_capacity(messages.len());
-        
+
         for mut message in messages {
+            // `content` is always caller-supplied text (the message body, or
+            // a caption on media messages) even when an encrypted binary
+            // `attachment` is also present, so it always needs validation.
             // Content validation
             if message.content.len() > 10000 {
                 message.content = message.content[..10000].to_string();
             }
-            
+
             // Remove potentially dangerous content
             message.content = self.sanitize_content(&message.content);
-            
+
+            // Media messages must already carry an encrypted attachment by
+            // the time they reach here (the caller encrypts it with
+            // `encrypt_attachment` before queueing, since the key is never
+            // stored server-side); drop anything that doesn't rather than
+            // silently forwarding a media message with no payload.
+            if Self::requires_attachment(&message.message_type)
+                && !self.attachment_is_well_formed(message.attachment.as_ref())
+            {
+                self.record_error().await;
+                continue;
+            }
+
             // Add processing timestamp
             message.timestamp = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs();
-            
+
             processed.push(message);
         }
-        
+
         Ok(processed)
     }
 
+    fn requires_attachment(message_type: &MessageType) -> bool {
+        matches!(
+            message_type,
+            MessageType::Image | MessageType::File | MessageType::Voice | MessageType::Video
+        )
+    }
+
+    // The processor never holds the caller's attachment key, so it can't
+    // decrypt or re-verify the fingerprint here; it can only check that the
+    // attachment is structurally intact (present, non-empty, valid base64
+    // frames) before letting the message continue downstream.
+    fn attachment_is_well_formed(&self, attachment: Option<&EncryptedAttachment>) -> bool {
+        let Some(attachment) = attachment else {
+            return false;
+        };
+        if attachment.frames.is_empty() || attachment.key_fingerprint.is_empty() {
+            return false;
+        }
+        attachment
+            .frames
+            .iter()
+            .all(|frame| base64::decode(frame).is_ok())
+    }
+
+    // Record a processing failure (e.g. a media message with a missing or
+    // corrupt attachment) in the real-time metrics.
+    async fn record_error(&self) {
+        if let Ok(mut metrics) = self.processing_metrics.write() {
+            metrics.errors_count += 1;
+        }
+    }
+
     // Content sanitization
     fn sanitize_content(&self, content: &str) -> String {
         // Remove script tags, SQL injection attempts, etc.
@@ -185,8 +259,9 @@ impl RustMessageProcessor {
                     .unwrap()
                     .as_secs(),
                 message_type: MessageType::Text,
+                attachment: None,
             };
-            
+
             self.queue_message(message).await?;
         }
         
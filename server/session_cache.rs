@@ -0,0 +1,216 @@
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+// How many recent counters `key_for_counter` remembers per cached secret,
+// mirroring handshake.rs's sliding replay window so a captured resumed
+// message can't simply be re-decrypted by resubmitting it verbatim.
+const REPLAY_WINDOW_SIZE: u64 = 1024;
+
+#[derive(Default)]
+struct ReplayWindow {
+    highest_seen: Option<u64>,
+    seen: HashSet<u64>,
+}
+
+impl ReplayWindow {
+    // Returns true the first time `counter` is presented (and records it),
+    // false on any repeat or any counter that has fallen out of the window.
+    fn check_and_record(&mut self, counter: u64) -> bool {
+        if let Some(highest) = self.highest_seen {
+            if counter.saturating_add(REPLAY_WINDOW_SIZE) <= highest {
+                return false;
+            }
+        }
+        if !self.seen.insert(counter) {
+            return false;
+        }
+
+        let highest = self.highest_seen.map_or(counter, |h| h.max(counter));
+        self.highest_seen = Some(highest);
+        self.seen
+            .retain(|c| c.saturating_add(REPLAY_WINDOW_SIZE) > highest);
+        true
+    }
+}
+
+/// Configuration for the in-memory resumption cache: how many peer secrets
+/// to retain and for how long before they are treated as expired.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionCacheConfig {
+    pub max_entries: usize,
+    pub ttl: Duration,
+}
+
+impl Default for SessionCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 1000,
+            ttl: Duration::from_secs(3600),
+        }
+    }
+}
+
+struct CachedSecret {
+    session_id: String,
+    secret: [u8; 32],
+    created_at: Instant,
+    ratchet_counter: u64,
+    consumed_counters: ReplayWindow,
+}
+
+/// Where session-resumption secrets are kept between messages. `Cache` trades
+/// memory for CPU by skipping RSA on subsequent messages with a peer;
+/// `NoSessionStorage` disables resumption entirely for deployments that care
+/// more about forward secrecy than per-message RSA cost.
+#[derive(Clone)]
+pub enum SessionStorage {
+    Cache(Arc<RwLock<ResumptionCache>>),
+    NoSessionStorage,
+}
+
+impl SessionStorage {
+    pub fn cache(config: SessionCacheConfig) -> Self {
+        SessionStorage::Cache(Arc::new(RwLock::new(ResumptionCache::new(config))))
+    }
+}
+
+/// An HKDF-ratcheted cache of per-peer resumption secrets, keyed by peer
+/// identity. The first hybrid exchange with a peer establishes the secret;
+/// subsequent messages derive fresh per-message keys from it instead of
+/// paying for another RSA-4096 operation.
+pub struct ResumptionCache {
+    entries: HashMap<String, CachedSecret>,
+    config: SessionCacheConfig,
+}
+
+impl ResumptionCache {
+    pub fn new(config: SessionCacheConfig) -> Self {
+        Self {
+            entries: HashMap::new(),
+            config,
+        }
+    }
+
+    // Store a freshly established secret for `peer_id`, evicting the oldest
+    // entry first if the cache is at capacity.
+    pub fn insert(&mut self, peer_id: &str, secret: [u8; 32]) -> String {
+        if self.entries.len() >= self.config.max_entries && !self.entries.contains_key(peer_id) {
+            if let Some(oldest_peer) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.created_at)
+                .map(|(peer, _)| peer.clone())
+            {
+                self.entries.remove(&oldest_peer);
+            }
+        }
+
+        let session_id = hex::encode(blake3::hash(secret.as_ref()).as_bytes());
+        self.entries.insert(
+            peer_id.to_string(),
+            CachedSecret {
+                session_id: session_id.clone(),
+                secret,
+                created_at: Instant::now(),
+                ratchet_counter: 0,
+                consumed_counters: ReplayWindow::default(),
+            },
+        );
+        session_id
+    }
+
+    // Derive the next per-message key for `peer_id` via an HKDF ratchet over
+    // the cached secret, returning the key plus the session id the receiver
+    // can use to confirm it is reading from the same cached secret.
+    // Returns the next per-message key, the session id, and the counter it
+    // was derived at (to be carried alongside the ciphertext so the
+    // receiver can re-derive the same key with `key_for_counter`).
+    pub fn next_key(&mut self, peer_id: &str) -> Option<([u8; 32], String, u64)> {
+        let expired = self.entries.get(peer_id)?.created_at.elapsed() >= self.config.ttl;
+        if expired {
+            self.entries.remove(peer_id);
+            return None;
+        }
+
+        let cached = self.entries.get_mut(peer_id)?;
+        let counter = cached.ratchet_counter;
+        let key = Self::ratchet(&cached.secret, counter);
+        cached.ratchet_counter += 1;
+        Some((key, cached.session_id.clone(), counter))
+    }
+
+    // Re-derive the key for a given (peer, session_id, counter) triple on
+    // the receiving side. Does not mutate the cache's own ratchet position,
+    // but does record `counter` as consumed so a captured message can't be
+    // replayed through this same path to decrypt "new" a second time.
+    pub fn key_for_counter(&mut self, peer_id: &str, session_id: &str, counter: u64) -> Option<[u8; 32]> {
+        let cached = self.entries.get_mut(peer_id)?;
+        if cached.session_id != session_id || cached.created_at.elapsed() >= self.config.ttl {
+            return None;
+        }
+        if !cached.consumed_counters.check_and_record(counter) {
+            return None;
+        }
+        Some(Self::ratchet(&cached.secret, counter))
+    }
+
+    fn ratchet(secret: &[u8; 32], counter: u64) -> [u8; 32] {
+        let hkdf = Hkdf::<Sha256>::new(None, secret);
+        let mut info = b"ulta-secure-messenger resumption ratchet".to_vec();
+        info.extend_from_slice(&counter.to_be_bytes());
+        let mut key = [0u8; 32];
+        hkdf.expand(&info, &mut key)
+            .expect("HKDF expand with fixed-size output cannot fail");
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ratchet_is_deterministic_and_advances() {
+        let mut cache = ResumptionCache::new(SessionCacheConfig::default());
+        let session_id = cache.insert("peer-1", [7u8; 32]);
+
+        let (first, first_session, first_counter) = cache.next_key("peer-1").unwrap();
+        let (second, second_session, second_counter) = cache.next_key("peer-1").unwrap();
+
+        assert_eq!(first_session, session_id);
+        assert_eq!(second_session, session_id);
+        assert_ne!(first, second);
+        assert_eq!(first_counter, 0);
+        assert_eq!(second_counter, 1);
+
+        let rederived = cache.key_for_counter("peer-1", &session_id, first_counter).unwrap();
+        assert_eq!(rederived, first);
+    }
+
+    #[test]
+    fn test_key_for_counter_rejects_replayed_counter() {
+        let mut cache = ResumptionCache::new(SessionCacheConfig::default());
+        let session_id = cache.insert("peer-1", [7u8; 32]);
+        let (_key, _session, counter) = cache.next_key("peer-1").unwrap();
+
+        assert!(cache.key_for_counter("peer-1", &session_id, counter).is_some());
+        assert!(cache.key_for_counter("peer-1", &session_id, counter).is_none());
+    }
+
+    #[test]
+    fn test_evicts_oldest_entry_at_capacity() {
+        let mut cache = ResumptionCache::new(SessionCacheConfig {
+            max_entries: 1,
+            ttl: Duration::from_secs(60),
+        });
+        cache.insert("peer-a", [1u8; 32]);
+        cache.insert("peer-b", [2u8; 32]);
+
+        assert!(cache.next_key("peer-a").is_none());
+        assert!(cache.next_key("peer-b").is_some());
+    }
+}
@@ -1,3 +1,4 @@
+use subtle::ConstantTimeEq;
 use wasm_bindgen::prelude::*;
 use js_sys::Promise;
 use web_sys::console;
@@ -46,29 +47,42 @@ impl UltraSecureCrypto {
 
     #[wasm_bindgen]
     pub fn secure_random(&self, length: usize) -> Vec<u8> {
-        // Generate cryptographically secure random bytes
+        // Generate cryptographically secure random bytes via the browser's
+        // crypto.getRandomValues (through the getrandom crate), replacing
+        // the non-cryptographic Math.random() this used to rely on.
         let mut buffer = vec![0u8; length];
-        
-        // In real implementation, this would use proper CSPRNG
-        // For demo, using simple random
-        for i in 0..length {
-            buffer[i] = (js_sys::Math::random() * 256.0) as u8;
-        }
-        
+        getrandom::getrandom(&mut buffer).expect("crypto.getRandomValues failed");
+
         console_log!("🦀 Generated {} secure random bytes", length);
         buffer
     }
 
+    // Compute a keyed BLAKE3 MAC over `message`, returned as a hex-encoded
+    // tag. Unlike a plain hash, the tag cannot be forged without the key.
+    #[wasm_bindgen]
+    pub fn mac(&self, key: &[u8], message: &str) -> String {
+        let tag = blake3::keyed_hash(&Self::mac_key(key), message.as_bytes());
+        hex::encode(tag.as_bytes())
+    }
+
     #[wasm_bindgen]
-    pub fn validate_message_integrity(&self, message: &str, signature: &str) -> bool {
-        // Message integrity validation using Rust performance
-        let computed_hash = self.fast_hash(message);
-        let is_valid = computed_hash == signature;
-        
-        console_log!("🦀 Message integrity check: {}", if is_valid { "VALID" } else { "INVALID" });
+    pub fn verify_mac(&self, key: &[u8], message: &str, tag: &str) -> bool {
+        let expected = blake3::keyed_hash(&Self::mac_key(key), message.as_bytes());
+        let is_valid = match hex::decode(tag) {
+            Ok(provided) => expected.as_bytes().ct_eq(provided.as_slice()).into(),
+            Err(_) => false,
+        };
+
+        console_log!("🦀 MAC verification: {}", if is_valid { "VALID" } else { "INVALID" });
         is_valid
     }
 
+    // Normalize a caller-supplied key of any length into the fixed 32-byte
+    // key BLAKE3's keyed mode requires.
+    fn mac_key(key: &[u8]) -> [u8; 32] {
+        blake3::hash(key).into()
+    }
+
     #[wasm_bindgen]
     pub fn get_performance_stats(&self) -> String {
         // Return performance statistics